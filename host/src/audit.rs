@@ -0,0 +1,70 @@
+//! Structured audit trail of every capability decision a skill component's
+//! `Host` impls make, replacing the ad hoc `[ALLOWED]`/`[DENIED]` prints with
+//! JSON Lines events suitable for security review and tooling. Every check
+//! in `filesystem_read`, `filesystem_write`, and `network` routes through
+//! `SkillHostState::audit` in `main.rs`, which is the single place that
+//! builds an `AuditEvent` and hands it to a sink here.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Decision {
+    Allowed,
+    Denied,
+}
+
+/// One capability check: who attempted what, on what, and why it was
+/// allowed or denied.
+#[derive(Debug, Serialize)]
+pub struct AuditEvent {
+    pub component: String,
+    pub interface: String,
+    pub operation: String,
+    pub argument: String,
+    pub decision: Decision,
+    pub reason: String,
+}
+
+/// Where audit events are written: stderr by default, or a `--audit-log`
+/// file (JSON Lines, one `AuditEvent` per line, appended to).
+pub enum AuditSink {
+    Stderr,
+    File(File),
+}
+
+impl AuditSink {
+    /// Opens the configured sink: `path` is the `--audit-log` argument, if
+    /// any; `None` falls back to stderr.
+    pub fn open(path: Option<&Path>) -> Result<Self> {
+        match path {
+            Some(path) => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("failed to open audit log '{}'", path.display()))?;
+                Ok(AuditSink::File(file))
+            }
+            None => Ok(AuditSink::Stderr),
+        }
+    }
+
+    /// Serializes `event` as one JSON Lines record and writes it to the
+    /// sink. A malformed event can't happen (all fields are plain strings or
+    /// the unit-variant `Decision`),
+    /// so serialization failure isn't handled as a recoverable error.
+    pub fn record(&mut self, event: &AuditEvent) {
+        let line = serde_json::to_string(event).expect("AuditEvent always serializes");
+        match self {
+            AuditSink::Stderr => eprintln!("{}", line),
+            AuditSink::File(file) => {
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+    }
+}
@@ -1,10 +1,217 @@
-use anyhow::{anyhow, Result};
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use anyhow::{anyhow, Context, Result};
+use clap::{Args, Parser, Subcommand};
+use std::cell::RefCell;
+use std::io::Read as _;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant};
 use wasmtime::component::{bindgen, Component, Linker, ResourceTable};
-use wasmtime::{Config, Engine, Store};
+use wasmtime::{Config, Engine, GuestProfiler, Store, StoreLimits, StoreLimitsBuilder};
 use wasmtime_wasi::{WasiCtx, WasiCtxBuilder, WasiView};
 
+mod audit;
+mod policy;
+use audit::{AuditEvent, AuditSink, Decision};
+use policy::Policy;
+
+/// How often the epoch-ticker thread bumps the engine's epoch counter.
+///
+/// The deadline callback compares wall-clock elapsed time against the
+/// requested timeout on every tick, so this just bounds how late a trap can
+/// fire relative to the deadline.
+const EPOCH_TICK: Duration = Duration::from_millis(50);
+
+/// Response body cap used when no `--max-memory` limit was set.
+const DEFAULT_MAX_RESPONSE_BYTES: usize = 10 * 1024 * 1024;
+
+/// Maximum number of redirects `http_get`/`http_post` will follow before
+/// giving up; each hop is re-checked against the egress allowlist.
+const MAX_REDIRECTS: u32 = 5;
+
+/// Spawns a background thread that increments `engine`'s epoch on a fixed
+/// tick so components running past their wall-clock budget get interrupted
+/// and an attached guest profiler gets sampled (fuel exhaustion traps on its
+/// own, without the epoch). The thread is detached and holds its own clone of `engine`,
+/// so it keeps ticking for the life of the process rather than exiting when
+/// the `Store` using it is dropped - harmless for this one-shot CLI, which
+/// exits once the requested command finishes.
+fn spawn_epoch_ticker(engine: Engine) {
+    thread::spawn(move || loop {
+        thread::sleep(EPOCH_TICK);
+        engine.increment_epoch();
+    });
+}
+
+/// Installs an epoch deadline callback that, on every epoch tick: samples
+/// `profiler` (if attached) and then traps once `timeout` (if set) has
+/// elapsed, otherwise keeps extending the deadline by one more tick.
+///
+/// Reuses the same epoch-ticker machinery for both `--timeout` and
+/// `--profile`, so enabling guest profiling doesn't need a second timer
+/// thread poking the store.
+fn watch_epoch<T>(
+    store: &mut Store<T>,
+    timeout: Option<Duration>,
+    profiler: Option<Rc<RefCell<GuestProfiler>>>,
+) {
+    let start = Instant::now();
+    let mut last_sample = start;
+    store.set_epoch_deadline(1);
+    store.epoch_deadline_callback(move |ctx| {
+        if let Some(profiler) = &profiler {
+            let now = Instant::now();
+            profiler.borrow_mut().sample(&ctx, now.duration_since(last_sample));
+            last_sample = now;
+        }
+        if let Some(timeout) = timeout {
+            if start.elapsed() >= timeout {
+                return Err(anyhow!("component exceeded its {:?} execution timeout", timeout));
+            }
+        }
+        Ok(wasmtime::UpdateDeadline::Continue(1))
+    });
+}
+
+/// Distinguishes *why* a component's execution was cut short so operators
+/// don't have to grep a wasmtime trap message to tell a timeout from an
+/// exhausted fuel budget or an over-sized allocation.
+#[derive(Debug)]
+enum ResourceLimitError {
+    FuelExhausted,
+    Timeout(Duration),
+    MemoryLimitExceeded,
+    Other(anyhow::Error),
+}
+
+impl std::fmt::Display for ResourceLimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResourceLimitError::FuelExhausted => {
+                write!(f, "component trapped: fuel exhausted (--max-fuel)")
+            }
+            ResourceLimitError::Timeout(d) => {
+                write!(f, "component trapped: exceeded {:?} timeout (--timeout)", d)
+            }
+            ResourceLimitError::MemoryLimitExceeded => {
+                write!(f, "component trapped: exceeded memory limit (--max-memory)")
+            }
+            ResourceLimitError::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ResourceLimitError {}
+
+/// Maps a wasmtime trap raised while calling into the guest to a specific
+/// [`ResourceLimitError`] variant, falling back to the original error when
+/// the trap doesn't match a known resource-limit signature.
+fn classify_trap(err: anyhow::Error, timeout: Option<Duration>) -> ResourceLimitError {
+    let msg = err.to_string();
+    if msg.contains("all fuel consumed") {
+        ResourceLimitError::FuelExhausted
+    } else if msg.contains("exceeded its") && msg.contains("execution timeout") {
+        ResourceLimitError::Timeout(timeout.unwrap_or_default())
+    } else if msg.contains("resource limit exceeded")
+        || msg.contains("memory minimum size")
+        || msg.contains("would exceed memory limit")
+    {
+        ResourceLimitError::MemoryLimitExceeded
+    } else {
+        ResourceLimitError::Other(err)
+    }
+}
+
+/// Shared resource-limit flags accepted by both `run` and `scan`.
+#[derive(Args, Clone, Default)]
+struct ResourceLimitArgs {
+    /// Maximum fuel (roughly, instructions) the component may consume before
+    /// it is trapped. Unlimited if omitted.
+    #[arg(long)]
+    max_fuel: Option<u64>,
+
+    /// Maximum size, in bytes, that each of the component's linear memories
+    /// may grow to.
+    #[arg(long)]
+    max_memory: Option<usize>,
+
+    /// Wall-clock seconds the component may run before it is trapped.
+    #[arg(long)]
+    timeout: Option<u64>,
+}
+
+impl ResourceLimitArgs {
+    fn configure(&self, config: &mut Config) {
+        if self.max_fuel.is_some() {
+            config.consume_fuel(true);
+        }
+    }
+
+    fn apply_fuel<T>(&self, store: &mut Store<T>) -> Result<()> {
+        if let Some(fuel) = self.max_fuel {
+            store.set_fuel(fuel)?;
+        }
+        Ok(())
+    }
+}
+
+/// `--profile <path>` flag shared by `run` and `scan`.
+#[derive(Args, Clone, Default)]
+struct ProfileArgs {
+    /// Sample the guest's call stack while it runs and write a Firefox
+    /// Profiler-format JSON profile to this path.
+    #[arg(long)]
+    profile: Option<PathBuf>,
+}
+
+/// Attaches a wasmtime `GuestProfiler` (if `--profile` was given) and/or a
+/// `--timeout` deadline to `store`, both driven by the same epoch-ticker
+/// thread. Returns the profiler handle so the caller can `finish_profile`
+/// it once the guest call returns.
+fn start_epoch_watch<T>(
+    engine: &Engine,
+    store: &mut Store<T>,
+    module_name: &str,
+    timeout: Option<Duration>,
+    profile_path: Option<&Path>,
+) -> Option<Rc<RefCell<GuestProfiler>>> {
+    if timeout.is_none() && profile_path.is_none() {
+        return None;
+    }
+    let profiler = profile_path
+        .map(|_| Rc::new(RefCell::new(GuestProfiler::new(module_name, EPOCH_TICK, Vec::new()))));
+    spawn_epoch_ticker(engine.clone());
+    watch_epoch(store, timeout, profiler.clone());
+    profiler
+}
+
+/// Finalizes a guest profile started by `start_epoch_watch`, writing it to
+/// the path the operator passed via `--profile`. No-op if profiling wasn't
+/// enabled.
+///
+/// `store`'s epoch-deadline callback (installed by `watch_epoch`) holds its
+/// own clone of `profiler`, so that clone has to be dropped before
+/// `Rc::try_unwrap` below can succeed - replacing it with a no-op callback
+/// does that.
+fn finish_profile<T>(
+    store: &mut Store<T>,
+    profiler: Option<Rc<RefCell<GuestProfiler>>>,
+    profile_path: Option<&Path>,
+) -> Result<()> {
+    let (Some(profiler), Some(path)) = (profiler, profile_path) else {
+        return Ok(());
+    };
+    store.epoch_deadline_callback(|_| Ok(wasmtime::UpdateDeadline::Continue(1)));
+    let profiler = Rc::try_unwrap(profiler)
+        .map_err(|_| anyhow!("guest profiler still has live references"))?
+        .into_inner();
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("failed to create profile file '{}'", path.display()))?;
+    profiler.finish(file);
+    println!("Wrote guest profile to {}", path.display());
+    Ok(())
+}
+
 // Generate bindings for skill-component world (imports capabilities)
 bindgen!({
     path: "../wit",
@@ -35,17 +242,41 @@ enum Commands {
         /// Path to the .wasm component file
         wasm_file: PathBuf,
 
-        /// Grant filesystem read capability
-        #[arg(long)]
-        allow_fs_read: bool,
+        /// Grant filesystem read capability, scoped to this directory.
+        /// Repeatable; a component may read within any of the given roots.
+        #[arg(long = "allow-fs-read")]
+        allow_fs_read: Vec<PathBuf>,
 
-        /// Grant filesystem write capability
-        #[arg(long)]
-        allow_fs_write: bool,
+        /// Grant filesystem write capability, scoped to this directory.
+        /// Repeatable; a component may write within any of the given roots.
+        #[arg(long = "allow-fs-write")]
+        allow_fs_write: Vec<PathBuf>,
 
         /// Grant network capability
         #[arg(long)]
         allow_network: bool,
+
+        /// Egress allowlist entry of the form `host` or `host:port`.
+        /// Repeatable; even with --allow-network, only these hosts are
+        /// reachable (default-deny, including on redirect).
+        #[arg(long = "allow-host")]
+        allow_host: Vec<String>,
+
+        /// Path to a TOML policy file of (component, object, action) rules;
+        /// when given, this supersedes the --allow-* flags entirely.
+        #[arg(long)]
+        policy: Option<PathBuf>,
+
+        /// Write capability decisions as JSON Lines to this file instead of
+        /// stderr.
+        #[arg(long = "audit-log")]
+        audit_log: Option<PathBuf>,
+
+        #[command(flatten)]
+        limits: ResourceLimitArgs,
+
+        #[command(flatten)]
+        profile: ProfileArgs,
     },
 
     /// Run a WASM scanner component (pure computation, no capabilities)
@@ -60,6 +291,12 @@ enum Commands {
         /// Read code from file instead of --code argument
         #[arg(short, long)]
         file: Option<PathBuf>,
+
+        #[command(flatten)]
+        limits: ResourceLimitArgs,
+
+        #[command(flatten)]
+        profile: ProfileArgs,
     },
 
     /// Run legacy demo (malicious or trusted component)
@@ -72,29 +309,346 @@ enum Commands {
 /// Host state containing permission context for skill components
 struct SkillHostState {
     component_name: String,
-    has_fs_read: bool,
-    has_fs_write: bool,
+    /// Preopened-style directory allowlists granted via `--allow-fs-read`/
+    /// `--allow-fs-write`. A path is only reachable if it canonicalizes to
+    /// somewhere inside one of these roots.
+    allowed_fs_read: Vec<PathBuf>,
+    allowed_fs_write: Vec<PathBuf>,
     has_network: bool,
+    /// Egress allowlist from `--allow-host`. When no `policy` is loaded it is
+    /// consulted on top of `has_network` for every outbound request,
+    /// including redirect targets, so granting the network capability alone
+    /// doesn't open arbitrary hosts. With a `policy`, it is not consulted.
+    allowed_hosts: Vec<String>,
+    /// Caps the buffered response body size; defaults to `max_memory` when
+    /// set, otherwise a conservative fallback.
+    max_response_bytes: usize,
+    /// When set (via `--policy`), consulted instead of the allowlists above
+    /// for every capability check.
+    policy: Option<Policy>,
+    /// Sink every capability decision is recorded to; see `Self::audit`.
+    audit_sink: AuditSink,
     table: ResourceTable,
     wasi_ctx: WasiCtx,
+    limits: StoreLimits,
 }
 
 impl SkillHostState {
-    fn new(component_name: &str, fs_read: bool, fs_write: bool, network: bool) -> Self {
+    fn new(
+        component_name: &str,
+        allowed_fs_read: Vec<PathBuf>,
+        allowed_fs_write: Vec<PathBuf>,
+        network: bool,
+        allowed_hosts: Vec<String>,
+        policy: Option<Policy>,
+        audit_sink: AuditSink,
+        limit_args: &ResourceLimitArgs,
+    ) -> Self {
         // Build a minimal WASI context (no filesystem, no network - we handle those ourselves)
         let wasi_ctx = WasiCtxBuilder::new()
             .build();
+        let mut limits_builder = StoreLimitsBuilder::new();
+        if let Some(max_memory) = limit_args.max_memory {
+            limits_builder = limits_builder.memory_size(max_memory);
+        }
+        let max_response_bytes = limit_args.max_memory.unwrap_or(DEFAULT_MAX_RESPONSE_BYTES);
         Self {
             component_name: component_name.to_string(),
-            has_fs_read: fs_read,
-            has_fs_write: fs_write,
+            allowed_fs_read,
+            allowed_fs_write,
             has_network: network,
+            allowed_hosts,
+            max_response_bytes,
+            policy,
+            audit_sink,
             table: ResourceTable::new(),
             wasi_ctx,
+            limits: limits_builder.build(),
+        }
+    }
+
+    /// Records one capability decision to the audit sink. This is the only
+    /// place `filesystem_read`, `filesystem_write`, and `network` should
+    /// report allow/deny outcomes.
+    fn audit(&mut self, interface: &str, operation: &str, argument: &str, decision: Decision, reason: impl Into<String>) {
+        self.audit_sink.record(&AuditEvent {
+            component: self.component_name.clone(),
+            interface: interface.to_string(),
+            operation: operation.to_string(),
+            argument: argument.to_string(),
+            decision,
+            reason: reason.into(),
+        });
+    }
+
+    /// Checks whether a network `action` against `url` is permitted.
+    ///
+    /// With `--policy` loaded, the policy engine is the sole source of
+    /// truth and its decision supersedes `--allow-host` entirely (every
+    /// redirect hop is re-checked against it too, in `http_request`). The
+    /// policy object is the URL's bare host (e.g. `api.github.com`), not the
+    /// full URL, matching the policy file's documented rule form. Otherwise
+    /// `--allow-network` must be set *and* the URL's host must be present in
+    /// the `--allow-host` egress allowlist - granting the network capability
+    /// alone reaches nothing, egress is default-deny until a host is
+    /// explicitly listed.
+    fn check_network_capability(&self, url: &str, action: &str) -> Result<(), String> {
+        if let Some(policy) = &self.policy {
+            let host = url_host(url).ok_or_else(|| format!("cannot parse host from URL '{}'", url))?;
+            return policy.enforce(&self.component_name, &host, action);
+        }
+        if !self.has_network {
+            return Err("Permission denied: network capability not granted".to_string());
+        }
+        let host_port = host_port_from_url(url)
+            .ok_or_else(|| format!("cannot parse host from URL '{}'", url))?;
+        if host_allowed(&self.allowed_hosts, &host_port) {
+            Ok(())
+        } else {
+            Err(format!(
+                "egress denied: host '{}' is not in the --allow-host allowlist",
+                host_port
+            ))
+        }
+    }
+
+    /// Checks whether `action` (`fs-read`/`fs-write`) on `path` is permitted
+    /// and, if so, returns the canonical path the operation should use.
+    ///
+    /// `path` is always canonicalized first (resolving `..` components and
+    /// symlinks), so an escape attempt can't hide inside either check below.
+    /// With `--policy` loaded, the policy engine is consulted against the
+    /// *canonicalized* path string. Otherwise the canonical path must fall
+    /// inside one of the directories granted for `action`.
+    fn check_fs_capability(&self, path: &str, action: &str) -> Result<PathBuf, String> {
+        if let Some(policy) = &self.policy {
+            let (_, resolved) = resolve_canonical(Path::new(path))?;
+            let object = resolved.to_string_lossy().into_owned();
+            policy.enforce(&self.component_name, &object, action)?;
+            return Ok(resolved);
+        }
+        let roots = match action {
+            "fs-read" => &self.allowed_fs_read,
+            "fs-write" => &self.allowed_fs_write,
+            _ => return Err(format!("unknown filesystem action '{}'", action)),
+        };
+        resolve_within_roots(Path::new(path), roots)
+    }
+}
+
+/// Canonicalizes `path`, resolving `..` components and symlinks, and returns
+/// `(dir_to_check, resolved)`:
+///
+/// - For a path that must already exist (`fs-read`, or `fs-write` to an
+///   existing file) both elements are the canonicalized path itself.
+/// - For a path whose file may not exist yet (`fs-write` creating a new
+///   file) `dir_to_check` is the canonicalized parent directory and
+///   `resolved` is that parent joined with the original file name, so
+///   containment can still be checked without requiring the file to exist.
+fn resolve_canonical(path: &Path) -> Result<(PathBuf, PathBuf), String> {
+    match std::fs::canonicalize(path) {
+        Ok(canonical) => Ok((canonical.clone(), canonical)),
+        Err(_) => {
+            let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or_else(|| Path::new("."));
+            let canonical_parent = std::fs::canonicalize(parent)
+                .map_err(|e| format!("cannot resolve directory of '{}': {}", path.display(), e))?;
+            let file_name = path
+                .file_name()
+                .ok_or_else(|| format!("'{}' has no file name", path.display()))?;
+            let resolved = canonical_parent.join(file_name);
+            // A dangling symlink fails to canonicalize but would still be
+            // followed by a write, so it can't be treated as a new file.
+            if std::fs::symlink_metadata(&resolved).map(|m| m.file_type().is_symlink()).unwrap_or(false) {
+                return Err(format!("'{}' is a symlink that cannot be resolved", path.display()));
+            }
+            Ok((canonical_parent, resolved))
         }
     }
 }
 
+/// Resolves `path` (see [`resolve_canonical`]) and verifies it is contained
+/// within one of `roots`, so neither `..` traversal nor a symlink can be
+/// used to escape a granted root.
+fn resolve_within_roots(path: &Path, roots: &[PathBuf]) -> Result<PathBuf, String> {
+    let (dir_to_check, resolved) = resolve_canonical(path)?;
+    let contained = roots.iter().any(|root| {
+        std::fs::canonicalize(root)
+            .map(|canonical_root| dir_to_check.starts_with(&canonical_root))
+            .unwrap_or(false)
+    });
+    if contained {
+        Ok(resolved)
+    } else {
+        Err(format!(
+            "path '{}' is outside the granted directories",
+            path.display()
+        ))
+    }
+}
+
+/// Extracts the `host:port` authority from a URL, defaulting the port to
+/// 80/443 based on scheme when the URL doesn't specify one, so it can be
+/// compared against `--allow-host` entries.
+fn host_port_from_url(url: &str) -> Option<String> {
+    let (rest, default_port) = if let Some(rest) = url.strip_prefix("https://") {
+        (rest, 443)
+    } else if let Some(rest) = url.strip_prefix("http://") {
+        (rest, 80)
+    } else {
+        return None;
+    };
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or(rest);
+    let authority = authority.rsplit('@').next().unwrap_or(authority);
+    if authority.is_empty() {
+        return None;
+    }
+    if authority.contains(':') {
+        Some(authority.to_string())
+    } else {
+        Some(format!("{}:{}", authority, default_port))
+    }
+}
+
+/// Extracts the bare host (no port) from a URL, for comparing against a
+/// policy rule's `object`, which per the policy file format is a host like
+/// `api.github.com` - not a full URL and not `host:port`.
+fn url_host(url: &str) -> Option<String> {
+    let host_port = host_port_from_url(url)?;
+    Some(host_port.split(':').next().unwrap_or(&host_port).to_string())
+}
+
+/// Checks `host_port` (`host:port`) against the `--allow-host` entries.
+/// An entry without a port (`example.com`) matches that host on any port;
+/// an entry with a port (`example.com:8443`) must match exactly.
+fn host_allowed(allowed_hosts: &[String], host_port: &str) -> bool {
+    let host = host_port.split(':').next().unwrap_or(host_port);
+    allowed_hosts.iter().any(|entry| {
+        if entry.contains(':') {
+            entry == host_port
+        } else {
+            entry == host
+        }
+    })
+}
+
+/// Resolves a `Location` redirect target against the URL it was served
+/// from, so a relative redirect still yields a fully-qualified URL whose
+/// host can be checked against the egress allowlist.
+fn resolve_redirect(base_url: &str, location: &str) -> String {
+    if location.starts_with("http://") || location.starts_with("https://") {
+        return location.to_string();
+    }
+    let scheme_end = base_url.find("://").map(|i| i + 3).unwrap_or(0);
+    let authority_end = base_url[scheme_end..]
+        .find('/')
+        .map(|i| scheme_end + i)
+        .unwrap_or(base_url.len());
+    if location.starts_with('/') {
+        return format!("{}{}", &base_url[..authority_end], location);
+    }
+    // Path-relative: resolve against the directory of the base URL's path.
+    let path = &base_url[authority_end..];
+    let path = path.split(['?', '#']).next().unwrap_or(path);
+    match path.rfind('/') {
+        Some(i) => format!("{}{}", &base_url[..authority_end + i + 1], location),
+        None => format!("{}/{}", &base_url[..authority_end], location),
+    }
+}
+
+/// Performs a blocking HTTP request through `ureq`, re-checking every
+/// redirect hop before following it and capping the buffered response body
+/// at `max_response_bytes`. When `policy` is `Some((policy, component_name,
+/// action))` each hop is re-enforced through the policy engine, matching
+/// `check_network_capability`; otherwise each hop must be in `allowed_hosts`
+/// (default-deny). Returns the real status code and body for any response
+/// the server sent, including 4xx/5xx. `Err` is returned for egress
+/// denials, DNS/connect/redirect errors, body read failures, and a response
+/// body over `max_response_bytes`.
+fn http_request(
+    method: &str,
+    url: &str,
+    body: Option<&str>,
+    allowed_hosts: &[String],
+    policy: Option<(&Policy, &str, &str)>,
+    max_response_bytes: usize,
+) -> Result<(u16, usize, String), String> {
+    let agent = ureq::AgentBuilder::new().redirects(0).build();
+    let mut current_url = url.to_string();
+
+    for _ in 0..=MAX_REDIRECTS {
+        match policy {
+            Some((policy, component_name, action)) => {
+                let host = url_host(&current_url)
+                    .ok_or_else(|| format!("cannot parse host from URL '{}'", current_url))?;
+                policy.enforce(component_name, &host, action)?;
+            }
+            None => {
+                let host_port = host_port_from_url(&current_url)
+                    .ok_or_else(|| format!("cannot parse host from URL '{}'", current_url))?;
+                if !host_allowed(allowed_hosts, &host_port) {
+                    return Err(format!(
+                        "egress denied: redirect host '{}' is not in the --allow-host allowlist",
+                        host_port
+                    ));
+                }
+            }
+        }
+
+        let request = match method {
+            "GET" => agent.get(&current_url),
+            "POST" => agent.post(&current_url),
+            other => return Err(format!("unsupported HTTP method '{}'", other)),
+        };
+        let outcome = match body {
+            Some(b) if method == "POST" => request.send_string(b),
+            _ => request.call(),
+        };
+
+        let response = match outcome {
+            Ok(response) => response,
+            // A 4xx/5xx still got a response from the server - ureq just
+            // reports it via `Err` instead of `Ok`. Treat it the same as a
+            // success so the guest gets the real status and body rather
+            // than a generic "request failed" error with no body; only an
+            // actual transport failure (the `Err(e)` arm below) has nothing
+            // to report back.
+            Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => return Err(format!("request to '{}' failed: {}", current_url, e)),
+        };
+
+        let status = response.status();
+        // The agent is built with `.redirects(0)`, so a 3xx comes back as
+        // `Ok(response)` rather than `Err(ureq::Error::Status(..))` (that
+        // variant is reserved for 4xx/5xx) - chase it ourselves so every hop
+        // gets re-checked against the egress allowlist/policy above.
+        if (300..400).contains(&status) {
+            let location = response
+                .header("Location")
+                .ok_or_else(|| format!("redirect ({}) with no Location header", status))?
+                .to_string();
+            current_url = resolve_redirect(&current_url, &location);
+            continue;
+        }
+
+        let mut buf = Vec::new();
+        response
+            .into_reader()
+            .take(max_response_bytes as u64 + 1)
+            .read_to_end(&mut buf)
+            .map_err(|e| format!("failed to read response body: {}", e))?;
+        if buf.len() > max_response_bytes {
+            return Err(format!(
+                "response from '{}' exceeded the {}-byte cap",
+                current_url, max_response_bytes
+            ));
+        }
+        let byte_count = buf.len();
+        return Ok((status, byte_count, String::from_utf8_lossy(&buf).into_owned()));
+    }
+
+    Err(format!("exceeded {} redirects", MAX_REDIRECTS))
+}
+
 impl WasiView for SkillHostState {
     fn table(&mut self) -> &mut ResourceTable {
         &mut self.table
@@ -108,25 +662,24 @@ impl WasiView for SkillHostState {
 // Implement filesystem-read interface
 impl sandbox::skill::filesystem_read::Host for SkillHostState {
     fn read_file(&mut self, path: String) -> Result<String, String> {
-        if !self.has_fs_read {
-            println!(
-                "[DENIED] Component '{}' attempted filesystem-read.read-file(\"{}\") without permission",
-                self.component_name, path
-            );
-            return Err(format!(
-                "Permission denied: filesystem-read capability not granted"
-            ));
-        }
+        let canonical = match self.check_fs_capability(&path, "fs-read") {
+            Ok(resolved) => resolved,
+            Err(reason) => {
+                self.audit("filesystem-read", "read-file", &path, Decision::Denied, reason.clone());
+                return Err(reason);
+            }
+        };
 
-        match std::fs::read_to_string(&path) {
+        match std::fs::read_to_string(&canonical) {
             Ok(content) => {
-                println!(
-                    "[ALLOWED] Component '{}' read file '{}' successfully",
-                    self.component_name, path
-                );
+                self.audit("filesystem-read", "read-file", &path, Decision::Allowed, "read succeeded");
                 Ok(content)
             }
-            Err(e) => Err(format!("Failed to read file '{}': {}", path, e)),
+            Err(e) => {
+                let reason = format!("Failed to read file '{}': {}", path, e);
+                self.audit("filesystem-read", "read-file", &path, Decision::Allowed, reason.clone());
+                Err(reason)
+            }
         }
     }
 }
@@ -134,25 +687,24 @@ impl sandbox::skill::filesystem_read::Host for SkillHostState {
 // Implement filesystem-write interface
 impl sandbox::skill::filesystem_write::Host for SkillHostState {
     fn write_file(&mut self, path: String, content: String) -> Result<(), String> {
-        if !self.has_fs_write {
-            println!(
-                "[DENIED] Component '{}' attempted filesystem-write.write-file(\"{}\") without permission",
-                self.component_name, path
-            );
-            return Err(format!(
-                "Permission denied: filesystem-write capability not granted"
-            ));
-        }
+        let canonical = match self.check_fs_capability(&path, "fs-write") {
+            Ok(resolved) => resolved,
+            Err(reason) => {
+                self.audit("filesystem-write", "write-file", &path, Decision::Denied, reason.clone());
+                return Err(reason);
+            }
+        };
 
-        match std::fs::write(&path, &content) {
+        match std::fs::write(&canonical, &content) {
             Ok(()) => {
-                println!(
-                    "[ALLOWED] Component '{}' wrote to file '{}' successfully",
-                    self.component_name, path
-                );
+                self.audit("filesystem-write", "write-file", &path, Decision::Allowed, "write succeeded");
                 Ok(())
             }
-            Err(e) => Err(format!("Failed to write file '{}': {}", path, e)),
+            Err(e) => {
+                let reason = format!("Failed to write file '{}': {}", path, e);
+                self.audit("filesystem-write", "write-file", &path, Decision::Allowed, reason.clone());
+                Err(reason)
+            }
         }
     }
 }
@@ -160,43 +712,71 @@ impl sandbox::skill::filesystem_write::Host for SkillHostState {
 // Implement network interface
 impl sandbox::skill::network::Host for SkillHostState {
     fn http_get(&mut self, url: String) -> Result<String, String> {
-        if !self.has_network {
-            println!(
-                "[DENIED] Component '{}' attempted network.http-get(\"{}\") without permission",
-                self.component_name, url
-            );
-            return Err(format!(
-                "Permission denied: network capability not granted"
-            ));
+        if let Err(reason) = self.check_network_capability(&url, "net-get") {
+            self.audit("network", "http-get", &url, Decision::Denied, reason.clone());
+            return Err(reason);
         }
 
-        println!(
-            "[ALLOWED] Component '{}' making HTTP GET to '{}'",
-            self.component_name, url
-        );
-        // Stub implementation - would use reqwest/ureq in production
-        Ok(format!("HTTP GET to {} - stub response", url))
+        let policy = self
+            .policy
+            .as_ref()
+            .map(|p| (p, self.component_name.as_str(), "net-get"));
+        match http_request("GET", &url, None, &self.allowed_hosts, policy, self.max_response_bytes) {
+            Ok((status, bytes, text)) => {
+                self.audit(
+                    "network",
+                    "http-get",
+                    &url,
+                    Decision::Allowed,
+                    format!("status {}, {} bytes received", status, bytes),
+                );
+                Ok(text)
+            }
+            Err(reason) => {
+                self.audit("network", "http-get", &url, Decision::Denied, reason.clone());
+                Err(reason)
+            }
+        }
     }
 
     fn http_post(&mut self, url: String, body: String) -> Result<String, String> {
-        if !self.has_network {
-            println!(
-                "[DENIED] Component '{}' attempted network.http-post(\"{}\") without permission",
-                self.component_name, url
-            );
-            return Err(format!(
-                "Permission denied: network capability not granted"
-            ));
+        if let Err(reason) = self.check_network_capability(&url, "net-post") {
+            self.audit("network", "http-post", &url, Decision::Denied, reason.clone());
+            return Err(reason);
         }
 
-        println!(
-            "[ALLOWED] Component '{}' making HTTP POST to '{}' with {} bytes",
-            self.component_name,
-            url,
-            body.len()
-        );
-        // Stub implementation
-        Ok(format!("HTTP POST to {} - stub response", url))
+        let policy = self
+            .policy
+            .as_ref()
+            .map(|p| (p, self.component_name.as_str(), "net-post"));
+        match http_request(
+            "POST",
+            &url,
+            Some(&body),
+            &self.allowed_hosts,
+            policy,
+            self.max_response_bytes,
+        ) {
+            Ok((status, bytes, text)) => {
+                self.audit(
+                    "network",
+                    "http-post",
+                    &url,
+                    Decision::Allowed,
+                    format!(
+                        "{} bytes sent, status {}, {} bytes received",
+                        body.len(),
+                        status,
+                        bytes
+                    ),
+                );
+                Ok(text)
+            }
+            Err(reason) => {
+                self.audit("network", "http-post", &url, Decision::Denied, reason.clone());
+                Err(reason)
+            }
+        }
     }
 }
 
@@ -204,14 +784,20 @@ impl sandbox::skill::network::Host for SkillHostState {
 struct ScannerHostState {
     table: ResourceTable,
     wasi_ctx: WasiCtx,
+    limits: StoreLimits,
 }
 
 impl ScannerHostState {
-    fn new() -> Self {
+    fn new(limit_args: &ResourceLimitArgs) -> Self {
         let wasi_ctx = WasiCtxBuilder::new().build();
+        let mut limits_builder = StoreLimitsBuilder::new();
+        if let Some(max_memory) = limit_args.max_memory {
+            limits_builder = limits_builder.memory_size(max_memory);
+        }
         Self {
             table: ResourceTable::new(),
             wasi_ctx,
+            limits: limits_builder.build(),
         }
     }
 }
@@ -226,28 +812,69 @@ impl WasiView for ScannerHostState {
     }
 }
 
+/// Renders a directory allowlist for the capability banner, e.g.
+/// `GRANTED (/data, /tmp/scratch)` or `DENIED`.
+fn describe_fs_roots(roots: &[PathBuf]) -> String {
+    if roots.is_empty() {
+        "DENIED".to_string()
+    } else {
+        format!(
+            "GRANTED ({})",
+            roots
+                .iter()
+                .map(|r| r.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
 fn run_skill_component(
     wasm_path: &PathBuf,
-    fs_read: bool,
-    fs_write: bool,
+    allowed_fs_read: Vec<PathBuf>,
+    allowed_fs_write: Vec<PathBuf>,
     network: bool,
+    allowed_hosts: Vec<String>,
+    policy_file: Option<&PathBuf>,
+    audit_log: Option<&PathBuf>,
+    limit_args: &ResourceLimitArgs,
+    profile_args: &ProfileArgs,
 ) -> Result<()> {
     let component_name = wasm_path
         .file_stem()
         .and_then(|s| s.to_str())
         .unwrap_or("unknown");
 
+    let policy = policy_file.map(|p| Policy::load(p)).transpose()?;
+    let audit_sink = AuditSink::open(audit_log.map(|p| p.as_path()))?;
+
     println!("=== WASM Sandbox - Running Skill Component ===");
     println!("Component: {}", wasm_path.display());
-    println!("Capabilities:");
-    println!("  filesystem-read:  {}", if fs_read { "GRANTED" } else { "DENIED" });
-    println!("  filesystem-write: {}", if fs_write { "GRANTED" } else { "DENIED" });
-    println!("  network:          {}", if network { "GRANTED" } else { "DENIED" });
+    if let Some(path) = policy_file {
+        println!("Policy: {} (supersedes --allow-* flags)", path.display());
+    } else {
+        println!("Capabilities:");
+        println!(
+            "  filesystem-read:  {}",
+            describe_fs_roots(&allowed_fs_read)
+        );
+        println!(
+            "  filesystem-write: {}",
+            describe_fs_roots(&allowed_fs_write)
+        );
+        println!("  network:          {}", if network { "GRANTED" } else { "DENIED" });
+    }
     println!();
 
     // Create engine with component model support
     let mut config = Config::new();
     config.wasm_component_model(true);
+    limit_args.configure(&mut config);
+    let needs_epoch = limit_args.timeout.is_some() || profile_args.profile.is_some();
+    if needs_epoch {
+        config.epoch_interruption(true);
+        config.debug_info(true);
+    }
     let engine = Engine::new(&config)?;
 
     // Load the component
@@ -265,12 +892,38 @@ fn run_skill_component(
     SkillComponent::add_to_linker(&mut linker, |state: &mut SkillHostState| state)?;
 
     // Create store with permission-aware host state
-    let state = SkillHostState::new(component_name, fs_read, fs_write, network);
+    let state = SkillHostState::new(
+        component_name,
+        allowed_fs_read,
+        allowed_fs_write,
+        network,
+        allowed_hosts,
+        policy,
+        audit_sink,
+        limit_args,
+    );
     let mut store = Store::new(&engine, state);
+    store.limiter(|state| &mut state.limits);
+    limit_args.apply_fuel(&mut store)?;
+    let profiler = start_epoch_watch(
+        &engine,
+        &mut store,
+        component_name,
+        limit_args.timeout.map(Duration::from_secs),
+        profile_args.profile.as_deref(),
+    );
 
     // Instantiate and run
     let instance = SkillComponent::instantiate(&mut store, &component, &linker)?;
-    let result = instance.sandbox_skill_skill().call_run(&mut store)?;
+    let call_result = instance.sandbox_skill_skill().call_run(&mut store);
+
+    // Finalize the profile whether the guest returned normally or trapped
+    // (e.g. a fuel/timeout limit tripped) - that's exactly the case a
+    // profile is most useful for, so it can't be skipped on the error path.
+    finish_profile(&mut store, profiler, profile_args.profile.as_deref())?;
+
+    let result =
+        call_result.map_err(|e| classify_trap(e, limit_args.timeout.map(Duration::from_secs)))?;
 
     println!();
     println!("=== Component Result ===");
@@ -279,16 +932,32 @@ fn run_skill_component(
     Ok(())
 }
 
-fn run_scanner_component(wasm_path: &PathBuf, code: &str) -> Result<()> {
+fn run_scanner_component(
+    wasm_path: &PathBuf,
+    code: &str,
+    limit_args: &ResourceLimitArgs,
+    profile_args: &ProfileArgs,
+) -> Result<()> {
     println!("=== WASM Sandbox - Running Scanner Component ===");
     println!("Scanner: {}", wasm_path.display());
     println!("Code length: {} bytes", code.len());
     println!("Capabilities: NONE (pure computation)");
     println!();
 
+    let component_name = wasm_path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("unknown");
+
     // Create engine with component model support
     let mut config = Config::new();
     config.wasm_component_model(true);
+    limit_args.configure(&mut config);
+    let needs_epoch = limit_args.timeout.is_some() || profile_args.profile.is_some();
+    if needs_epoch {
+        config.epoch_interruption(true);
+        config.debug_info(true);
+    }
     let engine = Engine::new(&config)?;
 
     // Load the component
@@ -305,15 +974,30 @@ fn run_scanner_component(wasm_path: &PathBuf, code: &str) -> Result<()> {
     wasmtime_wasi::add_to_linker_sync(&mut linker)?;
 
     // Create store
-    let state = ScannerHostState::new();
+    let state = ScannerHostState::new(limit_args);
     let mut store = Store::new(&engine, state);
+    store.limiter(|state| &mut state.limits);
+    limit_args.apply_fuel(&mut store)?;
+    let profiler = start_epoch_watch(
+        &engine,
+        &mut store,
+        component_name,
+        limit_args.timeout.map(Duration::from_secs),
+        profile_args.profile.as_deref(),
+    );
 
     // Instantiate and run
     let instance =
         scanner_bindings::ScannerComponent::instantiate(&mut store, &component, &linker)?;
-    let result = instance
-        .sandbox_skill_scanner()
-        .call_scan_code(&mut store, code)?;
+    let call_result = instance.sandbox_skill_scanner().call_scan_code(&mut store, code);
+
+    // Finalize the profile whether the guest returned normally or trapped
+    // (e.g. a fuel/timeout limit tripped) - that's exactly the case a
+    // profile is most useful for, so it can't be skipped on the error path.
+    finish_profile(&mut store, profiler, profile_args.profile.as_deref())?;
+
+    let result =
+        call_result.map_err(|e| classify_trap(e, limit_args.timeout.map(Duration::from_secs)))?;
 
     println!("=== Scan Result ===");
     println!("{}", result);
@@ -322,17 +1006,17 @@ fn run_scanner_component(wasm_path: &PathBuf, code: &str) -> Result<()> {
 }
 
 fn run_legacy_demo(component_name: &str) -> Result<()> {
-    let (wasm_path, fs_read, fs_write, network) = match component_name {
+    let (wasm_path, allowed_fs_read, allowed_fs_write, network) = match component_name {
         "malicious" => (
             PathBuf::from("target/wasm32-wasip1/release/component_malicious.wasm"),
-            false,
-            false,
+            vec![],
+            vec![],
             false,
         ),
         "trusted" => (
             PathBuf::from("target/wasm32-wasip1/release/component_trusted.wasm"),
-            true,
-            true,
+            vec![PathBuf::from(".")],
+            vec![PathBuf::from(".")],
             false,
         ),
         _ => {
@@ -343,7 +1027,17 @@ fn run_legacy_demo(component_name: &str) -> Result<()> {
         }
     };
 
-    run_skill_component(&wasm_path, fs_read, fs_write, network)
+    run_skill_component(
+        &wasm_path,
+        allowed_fs_read,
+        allowed_fs_write,
+        network,
+        vec![],
+        None,
+        None,
+        &ResourceLimitArgs::default(),
+        &ProfileArgs::default(),
+    )
 }
 
 fn main() -> Result<()> {
@@ -355,14 +1049,31 @@ fn main() -> Result<()> {
             allow_fs_read,
             allow_fs_write,
             allow_network,
+            allow_host,
+            policy,
+            audit_log,
+            limits,
+            profile,
         } => {
-            run_skill_component(&wasm_file, allow_fs_read, allow_fs_write, allow_network)?;
+            run_skill_component(
+                &wasm_file,
+                allow_fs_read,
+                allow_fs_write,
+                allow_network,
+                allow_host,
+                policy.as_ref(),
+                audit_log.as_ref(),
+                &limits,
+                &profile,
+            )?;
         }
 
         Commands::Scan {
             wasm_file,
             code,
             file,
+            limits,
+            profile,
         } => {
             let code_to_scan = match (code, file) {
                 (Some(c), _) => c,
@@ -374,7 +1085,7 @@ fn main() -> Result<()> {
                     ));
                 }
             };
-            run_scanner_component(&wasm_file, &code_to_scan)?;
+            run_scanner_component(&wasm_file, &code_to_scan, &limits, &profile)?;
         }
 
         Commands::Demo { component } => {
@@ -0,0 +1,98 @@
+//! Path/host-scoped capability policy, loaded from a TOML file.
+//!
+//! Expresses capabilities as per-`(component, object, action)` rules instead
+//! of the `--allow-fs-read`/`--allow-fs-write` directory lists and the
+//! `--allow-network`/`--allow-host` flags, so an operator can grant a
+//! component read access to `/data/*` without also handing it
+//! `/etc/passwd`. A `--policy <file>` on `run` supersedes the `--allow-*`
+//! flags entirely; see the `Policy::enforce` call sites in `main.rs`.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+/// A single allow/deny rule loaded from the policy file, e.g.
+///
+/// ```toml
+/// [[rule]]
+/// component = "component_trusted"
+/// object = "/data/*"
+/// action = "fs-read"
+/// decision = "allow"
+/// ```
+#[derive(Debug, Deserialize, Clone)]
+pub struct Rule {
+    pub component: String,
+    pub object: String,
+    pub action: String,
+    pub decision: Decision,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Decision {
+    Allow,
+    Deny,
+}
+
+#[derive(Debug, Deserialize, Default)]
+struct PolicyFile {
+    #[serde(default, rename = "rule")]
+    rules: Vec<Rule>,
+}
+
+/// An ordered set of `(component, object, action)` rules, consulted by each
+/// `Host` impl before it touches a path or URL.
+///
+/// The first rule whose component and action match and whose object pattern
+/// matches the requested object (see `object_matches`) wins. If no rule matches, the request is
+/// rejected (default-deny).
+pub struct Policy {
+    rules: Vec<Rule>,
+}
+
+impl Policy {
+    /// Loads and parses a policy file. Accepts TOML; see `Rule` for the
+    /// expected shape.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read policy file '{}'", path.display()))?;
+        let file: PolicyFile = toml::from_str(&text)
+            .with_context(|| format!("failed to parse policy file '{}'", path.display()))?;
+        Ok(Self { rules: file.rules })
+    }
+
+    /// Returns `Ok(())` if `component` is allowed to perform `action` on
+    /// `object`, or `Err` with a human-readable reason otherwise.
+    pub fn enforce(&self, component: &str, object: &str, action: &str) -> Result<(), String> {
+        for rule in &self.rules {
+            if rule.component == component
+                && rule.action == action
+                && object_matches(&rule.object, object)
+            {
+                return match rule.decision {
+                    Decision::Allow => Ok(()),
+                    Decision::Deny => Err(format!(
+                        "policy denied {} '{}' for component '{}' (matched deny rule '{}')",
+                        action, object, component, rule.object
+                    )),
+                };
+            }
+        }
+        Err(format!(
+            "policy denied {} '{}' for component '{}' (no matching rule, default-deny)",
+            action, object, component
+        ))
+    }
+}
+
+/// Matches `object` against a policy pattern supporting a trailing `*` glob
+/// (`/data/*`) or an exact match otherwise. There is no support for a
+/// leading or mid-pattern `*` - a pattern like `*.github.com` is matched
+/// literally and will never match a real object.
+fn object_matches(pattern: &str, object: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => object.starts_with(prefix),
+        None => pattern == object,
+    }
+}